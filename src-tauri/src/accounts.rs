@@ -0,0 +1,61 @@
+//! Multi-account cookie jar storage.
+//!
+//! Each signed-in Proton account gets its own [`CookieStore`] so more than
+//! one account can stay logged in at once. Which jar a given request uses
+//! is resolved per-call from an `X-Proton-Account` header or a
+//! `/account/<id>/` path prefix, falling back to whichever account is
+//! currently active.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::cookie::CookieStore;
+
+pub type AccountId = String;
+
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// All accounts' jars behind one lock - simpler than a lock per jar, and
+/// cheap enough since jars are only ever touched for the duration of a
+/// single proxied request.
+pub type AccountJars = Arc<RwLock<HashMap<AccountId, CookieStore>>>;
+
+/// The account used for requests that don't name one explicitly.
+pub type ActiveAccount = Arc<RwLock<AccountId>>;
+
+/// Resolve which account a request belongs to, and the path to forward
+/// upstream once any account-routing prefix has been stripped.
+///
+/// Checks the `X-Proton-Account` header first, then a `/account/<id>/`
+/// path prefix. Returns `None` for the account id when neither is present,
+/// meaning the caller should fall back to [`ActiveAccount`].
+pub fn resolve_account(headers: &http::HeaderMap, path: &str) -> (Option<AccountId>, String) {
+    if let Some(id) = headers.get("x-proton-account").and_then(|v| v.to_str().ok()) {
+        if !id.is_empty() {
+            return (Some(id.to_string()), path.to_string());
+        }
+    }
+
+    if let Some(rest) = path.strip_prefix("/account/") {
+        if let Some((id, remainder)) = rest.split_once('/') {
+            if !id.is_empty() {
+                return (Some(id.to_string()), format!("/{}", remainder));
+            }
+        }
+    }
+
+    (None, path.to_string())
+}
+
+/// List known account ids.
+pub async fn list(jars: &AccountJars) -> Vec<AccountId> {
+    jars.read().await.keys().cloned().collect()
+}
+
+/// Drop an account's in-memory jar. Deleting its persisted file is the
+/// caller's job, since only the caller knows the app-data directory.
+pub async fn remove(jars: &AccountJars, id: &str) {
+    jars.write().await.remove(id);
+}
@@ -0,0 +1,97 @@
+//! Pluggable session/auth handling for the proxy.
+//!
+//! The proxy used to inline cookie injection and the hardcoded Proton
+//! headers directly in the request closure. [`AuthProvider`] pulls that out
+//! into a swappable, testable subsystem, and gives a path to automatic
+//! session renewal: when a provider reports a response needs reauth, the
+//! proxy asks it to refresh and retries the original request once.
+
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+use tokio::sync::mpsc;
+
+use crate::accounts::{AccountId, AccountJars};
+use crate::cookie::CookieStore;
+
+/// Decorates outgoing requests and reacts to incoming responses on behalf
+/// of a signed-in session. `request_host`/`request_path` identify the
+/// upstream call being made, so a cookie-backed provider can scope what it
+/// sends/stores the way RFC 6265 requires.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Add whatever headers this session needs (cookies, API version
+    /// headers, bearer tokens, ...) to an outgoing request.
+    async fn decorate_request(&self, builder: RequestBuilder, request_host: &str, request_path: &str) -> RequestBuilder;
+
+    /// Inspect a response's headers and status once it comes back, e.g. to
+    /// capture `Set-Cookie` or notice an upstream session rotation.
+    async fn on_response(&self, headers: &http::HeaderMap, status: u16, request_host: &str, request_path: &str);
+
+    /// Whether this status means the proxy should ask for a refresh and
+    /// retry the request once. Providers that can't refresh should always
+    /// return `false` here rather than relying on `reauth` failing.
+    fn needs_reauth(&self, status: u16) -> bool;
+
+    /// Attempt to refresh credentials. Only called when `needs_reauth`
+    /// returned `true`; on `Ok` the proxy retries the original request once,
+    /// on `Err` the original response is surfaced to the caller unchanged.
+    async fn reauth(&self) -> Result<(), String> {
+        Err("this auth provider does not support reauth".to_string())
+    }
+}
+
+/// The default provider: one account's slot in the shared cookie jar map,
+/// plus the static Proton headers the web client sends. Doesn't support
+/// refresh-token rotation yet (Proton's refresh endpoint needs a session to
+/// exist first), so `needs_reauth` always reports false.
+pub struct CookieAuthProvider {
+    jars: AccountJars,
+    account_id: AccountId,
+    flush: mpsc::UnboundedSender<()>,
+}
+
+impl CookieAuthProvider {
+    pub fn new(jars: AccountJars, account_id: AccountId, flush: mpsc::UnboundedSender<()>) -> Self {
+        Self { jars, account_id, flush }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for CookieAuthProvider {
+    async fn decorate_request(&self, mut builder: RequestBuilder, request_host: &str, request_path: &str) -> RequestBuilder {
+        {
+            let mut jars = self.jars.write().await;
+            let jar = jars.entry(self.account_id.clone()).or_insert_with(CookieStore::new);
+            if let Some(cookie_header) = jar.header_for(request_host, request_path) {
+                builder = builder.header("Cookie", cookie_header);
+            }
+        }
+
+        builder
+            .header("x-pm-appversion", "web-drive@5.0.0")
+            .header("x-pm-apiversion", "3")
+            .header("Origin", "https://drive.proton.me")
+            .header("Referer", "https://drive.proton.me/")
+    }
+
+    async fn on_response(&self, headers: &http::HeaderMap, _status: u16, request_host: &str, request_path: &str) {
+        let mut stored_cookie = false;
+        for (name, value) in headers.iter() {
+            if name.as_str().to_lowercase() == "set-cookie" {
+                if let Ok(cookie_str) = value.to_str() {
+                    let mut jars = self.jars.write().await;
+                    let jar = jars.entry(self.account_id.clone()).or_insert_with(CookieStore::new);
+                    jar.store_set_cookie(cookie_str, request_host, request_path);
+                    stored_cookie = true;
+                }
+            }
+        }
+        if stored_cookie {
+            let _ = self.flush.send(());
+        }
+    }
+
+    fn needs_reauth(&self, _status: u16) -> bool {
+        false
+    }
+}
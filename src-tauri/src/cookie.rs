@@ -0,0 +1,328 @@
+//! RFC 6265-ish cookie storage.
+//!
+//! Parses `Set-Cookie` headers into [`Cookie`] records that retain enough
+//! attribute data (`Domain`, `Path`, expiry, `Secure`, `HttpOnly`, `SameSite`)
+//! to be scoped correctly on replay, instead of flattening everything into a
+//! single `name=value` map.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// A single stored cookie, including the attributes needed to decide
+/// whether it applies to a given request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Host the cookie applies to. Domain cookies are stored without the
+    /// leading dot; matching against subdomains is done in [`CookieStore::header_for`].
+    pub domain: String,
+    /// `true` if the `Domain` attribute was present (a "domain" cookie that
+    /// also matches subdomains), `false` if this is a host-only cookie.
+    pub domain_was_set: bool,
+    pub path: String,
+    /// Absolute expiry, if any. `None` means a session cookie.
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+}
+
+impl Cookie {
+    fn is_expired(&self) -> bool {
+        matches!(self.expires, Some(exp) if exp <= SystemTime::now())
+    }
+
+    /// `true` for cookies worth persisting to disk. Session cookies (no
+    /// `Expires`/`Max-Age`) are intentionally dropped so a restart doesn't
+    /// resurrect a cookie the server meant to die with the browser session.
+    fn is_persistable(&self) -> bool {
+        self.expires.is_some() && !self.is_expired()
+    }
+}
+
+/// Key a stored cookie by `(name, domain, path)` so host-only and
+/// domain-scoped cookies with the same name don't collide.
+type CookieKey = (String, String, String);
+
+/// A jar of cookies scoped by domain and path, per RFC 6265.
+#[derive(Debug, Default)]
+pub struct CookieStore {
+    entries: HashMap<CookieKey, Cookie>,
+}
+
+impl CookieStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a single `Set-Cookie` header value and store the result,
+    /// defaulting `Domain`/`Path` from the request the header came from.
+    pub fn store_set_cookie(&mut self, set_cookie: &str, request_host: &str, request_path: &str) {
+        if let Some(cookie) = parse_set_cookie(set_cookie, request_host, request_path) {
+            let key = (cookie.name.clone(), cookie.domain.clone(), cookie.path.clone());
+            self.entries.insert(key, cookie);
+        }
+    }
+
+    /// Build the `Cookie` header value for a request to `host` + `path`,
+    /// dropping expired entries and sorting longest-path-first as RFC 6265
+    /// recommends.
+    pub fn header_for(&mut self, host: &str, path: &str) -> Option<String> {
+        self.entries.retain(|_, c| !c.is_expired());
+
+        let mut matching: Vec<&Cookie> = self
+            .entries
+            .values()
+            .filter(|c| domain_matches(host, &c.domain, c.domain_was_set) && path_matches(path, &c.path))
+            .collect();
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        matching.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+
+        Some(
+            matching
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Cookies worth writing to disk (excludes session-only cookies).
+    pub fn persistable(&self) -> Vec<Cookie> {
+        self.entries.values().filter(|c| c.is_persistable()).cloned().collect()
+    }
+
+    /// Rebuild a store from a previously persisted cookie list, dropping
+    /// anything that expired while the app was closed.
+    pub fn from_persisted(cookies: Vec<Cookie>) -> Self {
+        let mut store = Self::new();
+        for cookie in cookies {
+            if cookie.is_expired() {
+                continue;
+            }
+            let key = (cookie.name.clone(), cookie.domain.clone(), cookie.path.clone());
+            store.entries.insert(key, cookie);
+        }
+        store
+    }
+
+    /// Drop every stored cookie (used by `clear_session`).
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// `true` if `request_host` equals `cookie_domain`, or is a subdomain of it
+/// when the cookie is domain-scoped (`Domain` attribute was set).
+fn domain_matches(request_host: &str, cookie_domain: &str, domain_was_set: bool) -> bool {
+    let request_host = request_host.to_ascii_lowercase();
+    let cookie_domain = cookie_domain.to_ascii_lowercase();
+
+    if request_host == cookie_domain {
+        return true;
+    }
+
+    domain_was_set && request_host.ends_with(&format!(".{}", cookie_domain))
+}
+
+/// `true` if `cookie_path` is a prefix of `request_path` on a `/`-segment
+/// boundary (the RFC 6265 path-match algorithm).
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    if !request_path.starts_with(cookie_path) {
+        return false;
+    }
+    cookie_path.ends_with('/')
+        || request_path.len() == cookie_path.len()
+        || request_path.as_bytes()[cookie_path.len()] == b'/'
+}
+
+/// Directory portion of a request path, used as the default `Path` for a
+/// cookie that doesn't specify one.
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(idx) => request_path[..idx].to_string(),
+    }
+}
+
+/// Parse a full `Set-Cookie` header value into a [`Cookie`], defaulting
+/// `Domain`/`Path` from the request that produced it.
+pub fn parse_set_cookie(set_cookie: &str, request_host: &str, request_path: &str) -> Option<Cookie> {
+    let mut parts = set_cookie.split(';');
+
+    let (name, value) = {
+        let first = parts.next()?;
+        let mut kv = first.splitn(2, '=');
+        let name = kv.next()?.trim();
+        let value = kv.next()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        (name.to_string(), value.to_string())
+    };
+
+    let mut domain: Option<String> = None;
+    let mut path: Option<String> = None;
+    let mut max_age: Option<i64> = None;
+    let mut expires: Option<SystemTime> = None;
+    let mut secure = false;
+    let mut http_only = false;
+    let mut same_site: Option<String> = None;
+
+    for attr in parts {
+        let attr = attr.trim();
+        let mut kv = attr.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let val = kv.next().map(|v| v.trim().to_string());
+        match key.to_ascii_lowercase().as_str() {
+            "domain" => domain = val.map(|v| v.trim_start_matches('.').to_ascii_lowercase()),
+            "path" => path = val,
+            "max-age" => max_age = val.and_then(|v| v.parse().ok()),
+            "expires" => expires = val.as_deref().and_then(parse_http_date),
+            "secure" => secure = true,
+            "httponly" => http_only = true,
+            "samesite" => same_site = val,
+            _ => {}
+        }
+    }
+
+    // Max-Age wins over Expires per RFC 6265 §5.3.
+    let resolved_expires = match max_age {
+        Some(seconds) => {
+            let now = SystemTime::now();
+            Some(if seconds <= 0 {
+                now - Duration::from_secs(1)
+            } else {
+                now + Duration::from_secs(seconds as u64)
+            })
+        }
+        None => expires,
+    };
+
+    let domain_was_set = domain.is_some();
+    let domain = domain.unwrap_or_else(|| request_host.to_ascii_lowercase());
+    let path = path.unwrap_or_else(|| default_path(request_path));
+
+    Some(Cookie {
+        name,
+        value,
+        domain,
+        domain_was_set,
+        path,
+        expires: resolved_expires,
+        secure,
+        http_only,
+        same_site,
+    })
+}
+
+/// Parse an HTTP-date (`Expires` attribute) into a [`SystemTime`].
+///
+/// Accepts the RFC 1123 form Proton and most servers emit
+/// (`Wed, 21 Oct 2026 07:28:00 GMT`); that's the only form worth handling
+/// here since we control neither side strictly but Proton's stack sticks to it.
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    httpdate::parse_http_date(date).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_matches_exact_host_regardless_of_domain_attribute() {
+        assert!(domain_matches("drive.proton.me", "drive.proton.me", false));
+        assert!(domain_matches("drive.proton.me", "drive.proton.me", true));
+    }
+
+    #[test]
+    fn domain_matches_subdomain_only_when_domain_attribute_was_set() {
+        assert!(domain_matches("api.drive.proton.me", "drive.proton.me", true));
+        assert!(!domain_matches("api.drive.proton.me", "drive.proton.me", false));
+        assert!(!domain_matches("evildrive.proton.me", "drive.proton.me", true));
+    }
+
+    #[test]
+    fn path_matches_on_segment_boundaries_only() {
+        assert!(path_matches("/api/drive", "/api"));
+        assert!(path_matches("/api/", "/api"));
+        assert!(path_matches("/api", "/api"));
+        assert!(!path_matches("/apikeys", "/api"));
+    }
+
+    #[test]
+    fn default_path_is_the_request_path_directory() {
+        assert_eq!(default_path("/api/drive/v1"), "/api/drive");
+        assert_eq!(default_path("/api"), "/");
+        assert_eq!(default_path("/"), "/");
+    }
+
+    #[test]
+    fn max_age_takes_precedence_over_expires() {
+        let cookie = parse_set_cookie(
+            "Session=abc; Max-Age=3600; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "drive.proton.me",
+            "/api",
+        )
+        .unwrap();
+
+        // Max-Age=3600 resolves to roughly an hour from now; the Expires
+        // attribute (in the past) would have made this cookie expired.
+        assert!(!cookie.is_expired());
+    }
+
+    #[test]
+    fn a_zero_or_negative_max_age_expires_the_cookie_immediately() {
+        let cookie = parse_set_cookie("Session=abc; Max-Age=0", "drive.proton.me", "/api").unwrap();
+        assert!(cookie.is_expired());
+
+        let cookie = parse_set_cookie("Session=abc; Max-Age=-1", "drive.proton.me", "/api").unwrap();
+        assert!(cookie.is_expired());
+    }
+
+    #[test]
+    fn session_cookies_without_expires_or_max_age_are_not_persistable() {
+        let cookie = parse_set_cookie("Session=abc", "drive.proton.me", "/api").unwrap();
+        assert!(cookie.expires.is_none());
+        assert!(!cookie.is_persistable());
+    }
+
+    #[test]
+    fn cookies_with_a_future_expiry_are_persistable() {
+        let cookie = parse_set_cookie("Session=abc; Max-Age=3600", "drive.proton.me", "/api").unwrap();
+        assert!(cookie.is_persistable());
+    }
+
+    #[test]
+    fn parse_set_cookie_defaults_domain_and_path_from_the_request() {
+        let cookie = parse_set_cookie("Session=abc", "drive.proton.me", "/api/drive/v1").unwrap();
+        assert_eq!(cookie.domain, "drive.proton.me");
+        assert!(!cookie.domain_was_set);
+        assert_eq!(cookie.path, "/api/drive");
+    }
+
+    #[test]
+    fn header_for_only_returns_cookies_scoped_to_the_request() {
+        let mut store = CookieStore::new();
+        store.store_set_cookie("Session=abc; Domain=proton.me; Path=/api", "drive.proton.me", "/api/login");
+        store.store_set_cookie("Other=xyz; Path=/other", "drive.proton.me", "/other");
+
+        let header = store.header_for("drive.proton.me", "/api/drive").unwrap();
+        assert_eq!(header, "Session=abc");
+    }
+}
@@ -0,0 +1,73 @@
+//! In-memory network inspector.
+//!
+//! Every proxied call is recorded into a bounded ring buffer so a
+//! devtools-style panel (or a bug reporter) can see recent Proton API
+//! traffic without the user having to run the binary from a terminal.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+/// Headers whose values are swapped for a placeholder before they ever
+/// enter the log, since they carry live session credentials. Covers both
+/// request headers (`Cookie`, `Authorization`) and the response headers
+/// that establish a session in the first place (`Set-Cookie` carries
+/// Proton's auth/refresh tokens on every sign-in).
+const REDACTED_HEADERS: &[&str] = &["cookie", "authorization", "set-cookie", "set-cookie2", "proxy-authorization"];
+const REDACTED_PLACEHOLDER: &str = "[redacted]";
+
+/// Max number of requests kept in memory; oldest entries are dropped first.
+const CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkLogEntry {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub request_bytes: usize,
+    pub response_bytes: usize,
+    pub duration_ms: u128,
+    pub request_headers: Vec<(String, String)>,
+    pub response_headers: Vec<(String, String)>,
+}
+
+/// Redact sensitive header values, keeping the header name so the shape of
+/// the request is still visible in the log.
+pub fn redacted_headers(headers: &http::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str().to_string();
+            let redacted = REDACTED_HEADERS.contains(&name.to_lowercase().as_str());
+            let value = if redacted {
+                REDACTED_PLACEHOLDER.to_string()
+            } else {
+                value.to_str().unwrap_or("").to_string()
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Bounded ring buffer of recent proxied requests.
+#[derive(Default)]
+pub struct NetworkLog {
+    entries: VecDeque<NetworkLogEntry>,
+}
+
+impl NetworkLog {
+    pub fn push(&mut self, entry: NetworkLogEntry) {
+        if self.entries.len() >= CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn recent(&self) -> Vec<NetworkLogEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+pub type SharedNetworkLog = Arc<RwLock<NetworkLog>>;
@@ -0,0 +1,254 @@
+//! Encrypted, debounced persistence for the cookie jars.
+//!
+//! Each account's jar holds live Proton session credentials, so every jar
+//! file is encrypted at rest with a key from the OS keyring (falling back
+//! to a generated key file with restrictive permissions if no keyring is
+//! available). Writes are debounced so a burst of `Set-Cookie` responses
+//! during login doesn't thrash the disk.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng, rand_core::RngCore};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::accounts::{AccountId, AccountJars, DEFAULT_ACCOUNT};
+use crate::cookie::{Cookie, CookieStore};
+
+const KEYRING_SERVICE: &str = "proton-drive-for-linux";
+const KEYRING_USER: &str = "cookie-jar-key";
+const JAR_PREFIX: &str = "cookies-";
+const JAR_SUFFIX: &str = ".jar";
+const KEY_FILENAME: &str = ".cookie-jar.key";
+const FLUSH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Filesystem-safe form of an account id, since it can come from an
+/// `X-Proton-Account` header we don't otherwise constrain.
+fn sanitize_account_id(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn jar_path(app_data_dir: &Path, account_id: &str) -> PathBuf {
+    app_data_dir.join(format!("{}{}{}", JAR_PREFIX, sanitize_account_id(account_id), JAR_SUFFIX))
+}
+
+fn key_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(KEY_FILENAME)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// The jars' encryption key, resolved once at startup via [`resolve_key`]
+/// and handed around as managed state from then on. Resolving it more than
+/// once isn't safe: a keyring that's momentarily unreachable (locked
+/// session, D-Bus hiccup) would otherwise fall through to a freshly
+/// generated key on that call, silently orphaning any jar already written
+/// under the real one.
+#[derive(Clone, Copy)]
+pub struct JarKey(pub [u8; 32]);
+
+/// Resolve the jars' encryption key from the OS keyring, falling back to a
+/// generated key file (mode 0600) alongside the jars if no keyring backend
+/// is available (e.g. headless CI, some minimal Linux desktops). Shared
+/// across accounts - it's a local-machine-at-rest key, not a secret per se.
+///
+/// Call this once at startup and keep the result in managed state (see
+/// [`JarKey`]) rather than calling it again later.
+pub fn resolve_key(app_data_dir: &Path) -> Result<[u8; 32], String> {
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(load_or_create_key_file(app_data_dir)),
+    };
+
+    match entry.get_password() {
+        Ok(existing) => decode_key(&existing).ok_or_else(|| "keyring returned a malformed cookie jar key".to_string()),
+        Err(keyring::Error::NoEntry) => {
+            let key = generate_key();
+            entry
+                .set_password(&hex::encode(key))
+                .map_err(|e| format!("failed to store a new cookie jar key in the keyring: {e}"))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("failed to read the cookie jar key from the keyring: {e}")),
+    }
+}
+
+fn load_or_create_key_file(app_data_dir: &Path) -> [u8; 32] {
+    let path = key_path(app_data_dir);
+    if let Ok(existing) = std::fs::read(&path) {
+        if existing.len() == 32 {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&existing);
+            return key;
+        }
+    }
+
+    let key = generate_key();
+    if std::fs::create_dir_all(app_data_dir).is_ok() && std::fs::write(&path, key).is_ok() {
+        let _ = restrict_permissions(&path);
+    }
+    key
+}
+
+fn decode_key(hex_str: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(hex_str).ok()?;
+    if bytes.len() != 32 {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    Some(key)
+}
+
+fn generate_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// Encrypt `plaintext` as `nonce(12) || ciphertext`.
+fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    let cipher = Aes256Gcm::new_from_slice(key).expect("key is exactly 32 bytes");
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let mut out = nonce_bytes.to_vec();
+    out.extend(cipher.encrypt(nonce, plaintext).expect("encryption failed"));
+    out
+}
+
+fn decrypt(key: &[u8; 32], data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 12 {
+        return None;
+    }
+    let (nonce_bytes, ciphertext) = data.split_at(12);
+    let cipher = Aes256Gcm::new_from_slice(key).expect("key is exactly 32 bytes");
+    cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext).ok()
+}
+
+/// Load one account's persisted jar from disk, if present and decryptable.
+/// Returns an empty jar on any error (missing file, bad key, corrupt data)
+/// rather than failing startup.
+fn load_one(app_data_dir: &Path, key: &[u8; 32], account_id: &str) -> CookieStore {
+    let Ok(data) = std::fs::read(jar_path(app_data_dir, account_id)) else {
+        return CookieStore::new();
+    };
+
+    let Some(plaintext) = decrypt(key, &data) else {
+        eprintln!("[cookie-jar] Failed to decrypt jar for '{account_id}', starting with an empty one");
+        return CookieStore::new();
+    };
+
+    match serde_json::from_slice::<Vec<Cookie>>(&plaintext) {
+        Ok(cookies) => CookieStore::from_persisted(cookies),
+        Err(e) => {
+            eprintln!("[cookie-jar] Failed to parse jar for '{account_id}': {e}");
+            CookieStore::new()
+        }
+    }
+}
+
+/// Load every persisted account jar found under the app-data directory.
+/// Falls back to a single empty `default` account if none exist yet.
+pub fn load_all(app_data_dir: &Path, key: &JarKey) -> HashMap<AccountId, CookieStore> {
+    let mut jars = HashMap::new();
+
+    if let Ok(read_dir) = std::fs::read_dir(app_data_dir) {
+        for entry in read_dir.flatten() {
+            let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let Some(account_id) = file_name.strip_prefix(JAR_PREFIX).and_then(|s| s.strip_suffix(JAR_SUFFIX)) else {
+                continue;
+            };
+            let store = load_one(app_data_dir, &key.0, account_id);
+            jars.insert(account_id.to_string(), store);
+        }
+    }
+
+    if jars.is_empty() {
+        jars.insert(DEFAULT_ACCOUNT.to_string(), CookieStore::new());
+    }
+
+    jars
+}
+
+/// Encrypt and write one account's cookies to disk, replacing the file
+/// atomically so a crash mid-write can't corrupt it.
+fn write_to_disk(app_data_dir: &Path, key: &JarKey, account_id: &str, cookies: &[Cookie]) {
+    let Ok(plaintext) = serde_json::to_vec(cookies) else {
+        return;
+    };
+
+    if std::fs::create_dir_all(app_data_dir).is_err() {
+        return;
+    }
+
+    let path = jar_path(app_data_dir, account_id);
+    let tmp_path = path.with_extension("tmp");
+    let ciphertext = encrypt(&key.0, &plaintext);
+
+    if std::fs::write(&tmp_path, &ciphertext).is_ok() {
+        let _ = restrict_permissions(&tmp_path);
+        let _ = std::fs::rename(&tmp_path, &path);
+    }
+}
+
+/// Delete one account's persisted jar file (used by `clear_session`/`remove_account`).
+pub fn clear(app_data_dir: &Path, account_id: &str) {
+    let _ = std::fs::remove_file(jar_path(app_data_dir, account_id));
+}
+
+/// Write every account's persistable cookies to disk.
+async fn flush_all(app_data_dir: &Path, key: &JarKey, jars: &AccountJars) {
+    let snapshot: Vec<(AccountId, Vec<Cookie>)> = jars
+        .read()
+        .await
+        .iter()
+        .map(|(id, store)| (id.clone(), store.persistable()))
+        .collect();
+
+    for (account_id, cookies) in snapshot {
+        write_to_disk(app_data_dir, key, &account_id, &cookies);
+    }
+}
+
+/// Spawn the debounced flush task and return a sender: call `.send(())` on
+/// it after any jar mutation, and every account's jar is written to disk
+/// `FLUSH_DEBOUNCE` after the last of a burst of calls.
+pub fn spawn_flusher(app_data_dir: PathBuf, key: JarKey, jars: AccountJars) -> tokio::sync::mpsc::UnboundedSender<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            // Coalesce a burst of notifications into a single write pass.
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(FLUSH_DEBOUNCE) => break,
+                    more = rx.recv() => if more.is_none() { return },
+                }
+            }
+
+            flush_all(&app_data_dir, &key, &jars).await;
+        }
+    });
+
+    tx
+}
+
+/// Flush every account synchronously, for use on app shutdown.
+pub async fn flush_now(app_data_dir: &Path, key: &JarKey, jars: &AccountJars) {
+    flush_all(app_data_dir, key, jars).await;
+}